@@ -0,0 +1,142 @@
+//! Clean-room package builds inside a throwaway container.
+//!
+//! Building an AUR package means running an arbitrary `PKGBUILD` (and often
+//! fetching arbitrary build dependencies) on the host. Doing that in a
+//! disposable container keeps both the dependencies and the build scripts from
+//! touching the user's real system. The recipe that drives the container is a
+//! simple template with three placeholders, so that downstreams can swap the
+//! base image or inject extra `makepkg` flags without recompiling.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The default container recipe.
+///
+/// Three placeholders are substituted before the recipe is handed to the
+/// container runtime: `{{ image }}` for the base image, `{{ pkg }}` for the
+/// name of the package directory inside the build context, and `{{ flags }}`
+/// for any extra flags to pass to `makepkg`.
+pub const DEFAULT_TEMPLATE: &str = "\
+FROM {{ image }}
+RUN pacman -Sy --noconfirm --needed sudo
+RUN useradd --create-home --shell /bin/bash build \\
+ && install -d -m 0750 /etc/sudoers.d \\
+ && echo 'build ALL=(ALL) NOPASSWD: ALL' > /etc/sudoers.d/build
+COPY . /home/build/{{ pkg }}
+RUN chown -R build:build /home/build/{{ pkg }}
+USER build
+WORKDIR /home/build/{{ pkg }}
+RUN makepkg -s --noconfirm {{ flags }}
+";
+
+/// Things that can go wrong while building a package in a container.
+pub enum Error {
+    Io(std::io::Error),
+    /// The container runtime returned a non-zero exit code.
+    Build(String),
+    /// No `*.pkg.tar.*` artifacts were produced by the build.
+    NoArtifacts(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(v: std::io::Error) -> Self {
+        Self::Io(v)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Build(p) => write!(f, "container build failed for {}", p),
+            Error::NoArtifacts(p) => write!(f, "no packages were produced for {}", p),
+        }
+    }
+}
+
+/// Render the recipe `template` for a single package.
+pub fn render(template: &str, image: &str, pkg: &str, flags: &[String]) -> String {
+    template
+        .replace("{{ image }}", image)
+        .replace("{{ pkg }}", pkg)
+        .replace("{{ flags }}", &flags.join(" "))
+}
+
+/// Build a single cloned package inside a clean container.
+///
+/// The clone at `clone` is used as the container's build context, the rendered
+/// recipe is fed to the runtime on stdin, and every resulting `*.pkg.tar.*`
+/// artifact is copied out of the finished image into `builds`. Returns the
+/// paths of the copied artifacts.
+pub fn build(
+    image: &str,
+    flags: &[String],
+    clone: &Path,
+    builds: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    let pkg = clone
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string();
+    // Use the clone itself as the build context so the whole clones tree isn't
+    // tarred up and shipped to the daemon for every package.
+    let recipe = render(DEFAULT_TEMPLATE, image, &pkg, flags);
+    let tag = format!("aura/{}", pkg);
+
+    // Build the image, streaming the rendered recipe in on stdin so that no
+    // temporary `Dockerfile` needs to be written into the user's clone.
+    let status = {
+        use std::io::Write;
+        use std::process::Stdio;
+
+        let mut child = Command::new("docker")
+            .args(["build", "-t", &tag, "-f", "-"])
+            .arg(clone)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .expect("piped stdin")
+            .write_all(recipe.as_bytes())?;
+        child.wait()?
+    };
+    if !status.success() {
+        return Err(Error::Build(pkg));
+    }
+
+    // Run the image with the build directory bind-mounted. `makepkg
+    // --packagelist` names exactly the artifacts the build produced — no
+    // signatures — so we copy those out (as root, via the granted sudo, since
+    // the mount is owned by the host user) and echo the list back to map them
+    // onto their host paths.
+    std::fs::create_dir_all(builds)?;
+    let out = Command::new("docker")
+        .args(["run", "--rm", "-v"])
+        .arg(format!("{}:/out", builds.display()))
+        .args([&tag, "bash", "-c"])
+        .arg(format!(
+            "cd /home/build/{} && sudo cp $(makepkg --packagelist) /out/ && makepkg --packagelist",
+            pkg
+        ))
+        .output()?;
+    if !out.status.success() {
+        return Err(Error::Build(pkg));
+    }
+
+    // Map each listed artifact onto its copied-out path, keyed by file name so
+    // that rebuilding an already-present version is still reported correctly.
+    let copied: Vec<PathBuf> = String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .filter_map(|l| Path::new(l).file_name().map(|n| builds.join(n)))
+        .filter(|p| p.exists())
+        .collect();
+    if copied.is_empty() {
+        Err(Error::NoArtifacts(pkg))
+    } else {
+        Ok(copied)
+    }
+}
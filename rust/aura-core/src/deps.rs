@@ -0,0 +1,193 @@
+//! Recursive resolution of AUR dependencies.
+//!
+//! A user asking to install an AUR package is implicitly asking to install
+//! everything that package needs to build and run. Some of those dependencies
+//! live in the official repositories and can be handed straight to `pacman`;
+//! the rest are themselves AUR packages that must be cloned and built first,
+//! in an order that respects their own dependencies. This module walks the
+//! `depends`/`make_depends`/`check_depends` fields returned by
+//! [`crate::aur::info`], building a graph of the AUR-only packages and
+//! producing a topologically sorted build order with leaves first.
+
+use alpm::Alpm;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Things that can go wrong while resolving dependencies.
+pub enum Error {
+    Raur(raur_curl::Error),
+    /// A dependency cycle was detected among the listed AUR packages.
+    Cycle(Vec<String>),
+}
+
+impl From<raur_curl::Error> for Error {
+    fn from(v: raur_curl::Error) -> Self {
+        Self::Raur(v)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Raur(e) => write!(f, "{}", e),
+            Error::Cycle(c) => write!(f, "dependency cycle detected: {}", c.join(", ")),
+        }
+    }
+}
+
+/// The outcome of resolving a set of requested packages.
+pub struct Resolution {
+    /// AUR packages to build, leaves first, ready to clone and build in order.
+    pub to_build: Vec<String>,
+    /// Official-repository dependencies to hand to `pacman -S --asdeps`.
+    pub repo_deps: HashSet<String>,
+}
+
+/// Strip any version constraint from a dependency string.
+///
+/// Dependency strings may carry a constraint such as `glibc>=2.0`; only the
+/// bare package name is meaningful for resolution.
+fn bare_name(dep: &str) -> &str {
+    dep.split(|c| c == '<' || c == '>' || c == '=' || c == ':')
+        .next()
+        .unwrap_or(dep)
+        .trim()
+}
+
+/// Whether a dependency is already satisfied on the system.
+///
+/// A dependency is satisfied if a package of that name is installed, or if some
+/// installed package `provides` it (covering virtual packages such as
+/// `java-runtime` and `foo` provided by `foo-git`).
+fn satisfied(alpm: &Alpm, dep: &str) -> bool {
+    let db = alpm.localdb();
+    db.pkg(dep).is_ok() || db.pkgs().find_satisfier(dep).is_some()
+}
+
+/// Recursively resolve the AUR dependencies of the requested packages.
+///
+/// Starting from `packages`, each package's AUR dependencies are queried in
+/// turn. Dependencies already satisfied on the system (per `alpm`'s local
+/// database, including `provides`) are dropped, so an install only ever builds
+/// what's actually missing. Dependencies the AUR doesn't know about are assumed
+/// to be official-repository packages and collected separately. The resulting
+/// AUR graph is checked for cycles and returned in topological (leaves-first)
+/// order. The user's explicitly-requested packages are always (re)built, even
+/// if already installed.
+pub fn resolve(alpm: &Alpm, packages: &[&str]) -> Result<Resolution, Error> {
+    // adjacency: package -> its AUR dependencies
+    let mut graph: HashMap<String, Vec<String>> = HashMap::new();
+    let mut repo_deps: HashSet<String> = HashSet::new();
+
+    let roots: HashSet<String> = packages.iter().map(|p| p.to_string()).collect();
+    let mut queue: VecDeque<String> = packages.iter().map(|p| p.to_string()).collect();
+    let mut seen: HashSet<String> = queue.iter().cloned().collect();
+
+    while !queue.is_empty() {
+        // Resolve the whole current frontier in one AUR query.
+        let batch: Vec<String> = queue.drain(..).collect();
+        let infos = crate::aur::info(&batch)?;
+
+        // Names the AUR actually knows about; the rest are repo packages.
+        let known: HashSet<String> = infos.iter().map(|i| i.name.clone()).collect();
+        for name in batch.iter().filter(|n| !known.contains(*n)) {
+            repo_deps.insert(name.clone());
+        }
+
+        for info in infos {
+            let mut aur_deps = Vec::new();
+            let all = info
+                .depends
+                .iter()
+                .chain(info.make_depends.iter())
+                .chain(info.check_depends.iter());
+
+            for dep in all {
+                let name = bare_name(dep).to_string();
+                if name.is_empty() {
+                    continue;
+                }
+                // Already-satisfied dependencies need no work; requested
+                // packages are never skipped this way.
+                if !roots.contains(&name) && satisfied(alpm, &name) {
+                    continue;
+                }
+                // Defer the repo/AUR decision to when we query `name` itself;
+                // every newly seen dependency is enqueued once.
+                if seen.insert(name.clone()) {
+                    queue.push_back(name.clone());
+                }
+                aur_deps.push(name);
+            }
+
+            graph.insert(info.name.clone(), aur_deps);
+        }
+    }
+
+    // Anything queued but never returned by the AUR is a repo dependency and
+    // must not appear as an AUR build node.
+    let repo_deps: HashSet<String> = repo_deps
+        .into_iter()
+        .filter(|d| !graph.contains_key(d))
+        .collect();
+    for deps in graph.values_mut() {
+        deps.retain(|d| !repo_deps.contains(d));
+    }
+
+    let to_build = topo_sort(&graph)?;
+    Ok(Resolution {
+        to_build,
+        repo_deps,
+    })
+}
+
+/// Produce a leaves-first topological ordering of the AUR dependency graph.
+///
+/// Uses Kahn's algorithm; if any node remains unprocessed the graph contained
+/// a cycle, which is reported as an error.
+fn topo_sort(graph: &HashMap<String, Vec<String>>) -> Result<Vec<String>, Error> {
+    // Count how many dependents each node has pending (in-degree on reversed
+    // edges), so that a node is emitted only once all its dependents are
+    // emitted.
+    let mut indegree: HashMap<&str, usize> = graph.keys().map(|k| (k.as_str(), 0)).collect();
+    for deps in graph.values() {
+        for dep in deps {
+            if let Some(n) = indegree.get_mut(dep.as_str()) {
+                *n += 1;
+            }
+        }
+    }
+
+    // Start from the top: nodes nothing else depends on within the graph.
+    let mut ready: VecDeque<&str> = indegree
+        .iter()
+        .filter(|(_, &n)| n == 0)
+        .map(|(&k, _)| k)
+        .collect();
+
+    let mut order = Vec::with_capacity(graph.len());
+    while let Some(node) = ready.pop_front() {
+        order.push(node.to_string());
+        for dep in graph.get(node).into_iter().flatten() {
+            if let Some(n) = indegree.get_mut(dep.as_str()) {
+                *n -= 1;
+                if *n == 0 {
+                    ready.push_back(dep.as_str());
+                }
+            }
+        }
+    }
+
+    if order.len() == graph.len() {
+        // `order` currently lists dependents before their deps; reverse so that
+        // leaf dependencies are built first.
+        order.reverse();
+        Ok(order)
+    } else {
+        let cycle: Vec<String> = graph
+            .keys()
+            .filter(|k| !order.iter().any(|o| o == *k))
+            .cloned()
+            .collect();
+        Err(Error::Cycle(cycle))
+    }
+}
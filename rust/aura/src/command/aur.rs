@@ -5,24 +5,44 @@ use crate::{aura, dirs, green, red, yellow};
 use alpm::Alpm;
 use aura_core::aur::{PkgPartition, AUR_BASE_URL};
 use chrono::{TimeZone, Utc};
-use colored::{ColoredString, Colorize};
+use colored::{Color, ColoredString, Colorize};
 use i18n_embed::{fluent::FluentLanguageLoader, LanguageLoader};
 use i18n_embed_fl::fl;
 use log::debug;
+use pbr::ProgressBar;
 use rayon::prelude::*;
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use validated::Validated;
 
+/// The default base image used for clean-room container builds.
+pub const DEFAULT_BUILD_IMAGE: &str = "archlinux:base-devel";
+
 pub enum Error {
     Raur(raur_curl::Error),
     Dirs(crate::dirs::Error),
     Io(std::io::Error),
     Git(aura_core::git::Error),
+    Container(aura_core::container::Error),
+    Deps(aura_core::deps::Error),
     Silent,
 }
 
+impl From<aura_core::container::Error> for Error {
+    fn from(v: aura_core::container::Error) -> Self {
+        Self::Container(v)
+    }
+}
+
+impl From<aura_core::deps::Error> for Error {
+    fn from(v: aura_core::deps::Error) -> Self {
+        Self::Deps(v)
+    }
+}
+
 impl From<aura_core::git::Error> for Error {
     fn from(v: aura_core::git::Error) -> Self {
         Self::Git(v)
@@ -54,6 +74,8 @@ impl std::fmt::Display for Error {
             Error::Dirs(e) => write!(f, "{}", e),
             Error::Io(e) => write!(f, "{}", e),
             Error::Git(e) => write!(f, "{}", e),
+            Error::Container(e) => write!(f, "{}", e),
+            Error::Deps(e) => write!(f, "{}", e),
             Error::Silent => write!(f, ""),
         }
     }
@@ -144,6 +166,7 @@ pub(crate) fn search(
     rev: bool,
     limit: Option<usize>,
     quiet: bool,
+    color: Color,
     mut terms: Vec<String>,
 ) -> Result<(), Error> {
     let db = alpm.localdb();
@@ -155,6 +178,10 @@ pub(crate) fn search(
         t.make_ascii_lowercase();
     }
 
+    // Keep the full, lowercased term list around for highlighting; the search
+    // itself only needs the largest term.
+    let highlights = terms.clone();
+
     // Search using the largest term.
     let initial_term = terms.pop().unwrap();
     let mut matches: Vec<_> = aura_core::aur::search(&initial_term)?;
@@ -187,7 +214,7 @@ pub(crate) fn search(
         if quiet {
             println!("{}", p.name);
         } else {
-            let n = p.name.bold();
+            let n = highlight(color, &highlights, &p.name);
             let vot = format!("{}", p.num_votes).yellow();
             let pop = format!("{:.2}", p.popularity).yellow();
             let ver = match p.out_of_date {
@@ -199,15 +226,69 @@ pub(crate) fn search(
                 Ok(_) => "[installed]".bold(),
             };
 
-            // TODO Search term highlighting
             println!("{}{} {} ({} | {}) {}", rep, n, ver, vot, pop, ins);
-            println!("    {}", p.description.unwrap_or_default());
+            let desc = highlight(color, &highlights, &p.description.unwrap_or_default());
+            println!("    {}", desc);
         }
     }
 
     Ok(())
 }
 
+/// The default color used to highlight matched search terms.
+pub(crate) const DEFAULT_HIGHLIGHT: Color = Color::Cyan;
+
+/// Highlight every occurrence of `terms` within `text`.
+///
+/// Matching is ASCII case-insensitive; `terms` are expected to already be
+/// lowercased by the caller. Overlapping or adjacent matches are merged into a single span
+/// so that a region is never wrapped in ANSI codes twice, which would corrupt
+/// the `colored` output.
+pub(crate) fn highlight(color: Color, terms: &[String], text: &str) -> String {
+    // Match against the original bytes, folding only ASCII case; any other
+    // byte must match exactly. Since each term is itself valid UTF-8, the
+    // resulting spans always fall on char boundaries, unlike offsets taken
+    // from a non-length-preserving `to_lowercase()`.
+    let bytes = text.as_bytes();
+
+    // Byte spans [start, end) of every individual match.
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for t in terms.iter().filter(|t| !t.is_empty()) {
+        let tb = t.as_bytes();
+        let mut i = 0;
+        while i + tb.len() <= bytes.len() {
+            if bytes[i..i + tb.len()].eq_ignore_ascii_case(tb) {
+                spans.push((i, i + tb.len()));
+            }
+            i += 1;
+        }
+    }
+    if spans.is_empty() {
+        return text.to_string();
+    }
+
+    // Merge overlapping and adjacent spans so each region is wrapped once.
+    spans.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(spans.len());
+    for (s, e) in spans {
+        match merged.last_mut() {
+            Some(last) if s <= last.1 => last.1 = last.1.max(e),
+            _ => merged.push((s, e)),
+        }
+    }
+
+    // Rebuild the string, coloring the merged spans and leaving the rest plain.
+    let mut out = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (s, e) in merged {
+        out.push_str(&text[cursor..s]);
+        out.push_str(&format!("{}", text[s..e].color(color).bold()));
+        cursor = e;
+    }
+    out.push_str(&text[cursor..]);
+    out
+}
+
 /// Open a given package's AUR package in a browser.
 pub(crate) fn open(package: &str) -> Result<(), std::io::Error> {
     let url = package_url(package);
@@ -230,14 +311,19 @@ pub(crate) fn clone_aur_repos(
     fll: &FluentLanguageLoader,
     packages: &[String],
 ) -> Result<(), Error> {
+    // A single aggregate bar tracks how many clones have finished; each worker
+    // advances it and reports the package it just handled as the bar message.
+    let pb = Arc::new(Mutex::new(ProgressBar::new(packages.len() as u64)));
     let clones: Validated<(), &str> = packages
         .par_iter()
         .map(|p| {
             let pkg = p.as_str();
-            aura!(fll, "A-w", package = pkg);
-            clone_aur_repo(None, &p).map_err(|_| pkg).void()
+            let r = clone_aur_repo(None, p).map_err(|_| pkg).void();
+            tick(&pb, pkg, r.is_err());
+            r
         })
         .collect();
+    pb.lock().unwrap().finish_println("");
 
     match clones {
         Validated::Good(_) => {
@@ -256,21 +342,51 @@ pub(crate) fn clone_aur_repos(
     }
 }
 
-// TODO Add a progress bar here.
+/// Advance a shared progress bar, noting the package just processed and
+/// surfacing any failure inline rather than only at the end.
+fn tick(pb: &Arc<Mutex<ProgressBar<std::io::Stdout>>>, pkg: &str, failed: bool) {
+    let mut bar = pb.lock().unwrap();
+    if failed {
+        bar.message(&format!("FAILED {} ", pkg));
+    } else {
+        bar.message(&format!("{} ", pkg));
+    }
+    bar.inc();
+}
+
 /// Pull the latest commits from every clone in the `packages` directory.
 pub(crate) fn refresh(fll: &FluentLanguageLoader) -> Result<(), Error> {
-    let pulls: Validated<(), String> = dirs::clones()?
+    // Collect the clones up front so the progress bar knows its total.
+    let clones: Vec<(String, PathBuf)> = dirs::clones()?
         .read_dir()?
         .filter_map(|rde| rde.ok())
         .filter(|de| de.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
-        .par_bridge()
         .filter_map(|de| de.file_name().into_string().ok().map(|p| (p, de.path())))
-        .map(|(pkg, path)| aura_core::git::pull(&path).map_err(|_| pkg.clone()))
         .collect();
 
+    let pb = Arc::new(Mutex::new(ProgressBar::new(clones.len() as u64)));
+    let pulls: Validated<(), String> = clones
+        .par_iter()
+        .map(|(pkg, path)| {
+            let r = aura_core::git::pull(path).map_err(|_| pkg.clone());
+            tick(&pb, pkg, r.is_err());
+            r.void()
+        })
+        .collect();
+    pb.lock().unwrap().finish_println("");
+
     match pulls {
         Validated::Good(_) => {
             green!(fll, "common-done");
+            // Audit any freshly pulled build scripts before returning. This is
+            // done sequentially since the review prompt is interactive.
+            for de in dirs::clones()?
+                .read_dir()?
+                .filter_map(|rde| rde.ok())
+                .filter(|de| de.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
+            {
+                review_changes(fll, &de.path())?;
+            }
             Ok(())
         }
         Validated::Fail(bads) => {
@@ -288,30 +404,175 @@ pub(crate) fn refresh(fll: &FluentLanguageLoader) -> Result<(), Error> {
 // TODO Thu Jan 13 17:41:55 2022
 //
 // This will obviously require more arguments.
-pub(crate) fn install(fll: &FluentLanguageLoader, pkgs: &[String]) -> Result<(), Error> {
+pub(crate) fn install(
+    fll: &FluentLanguageLoader,
+    alpm: &Alpm,
+    containerize: bool,
+    image: &str,
+    pkgs: &[String],
+) -> Result<(), Error> {
     // Exit early if the user passed no packages.
     if pkgs.is_empty() {
         red!(fll, "common-no-packages");
         return Err(Error::Silent);
     }
 
+    // Validate the user's requests against the AUR before resolving any
+    // dependencies.
     let (cloned, to_clone) = real_packages(fll, pkgs)?;
     debug!("Already cloned: {:?}", cloned);
     debug!("To clone: {:?}", to_clone);
 
+    // Recursively resolve the full AUR dependency tree and the order in which
+    // those packages must be built.
+    let requested: Vec<&str> = cloned.iter().chain(to_clone.iter()).copied().collect();
+    let aura_core::deps::Resolution {
+        to_build,
+        repo_deps,
+    } = aura_core::deps::resolve(alpm, &requested)?;
+
+    // Show the resolved plan and get confirmation before touching the system.
+    display_plan(fll, &to_build, &repo_deps);
+    let msg = format!("{} {} ", fl!(fll, "proceed"), fl!(fll, "proceed-yes"));
+    crate::utils::prompt(&crate::a!(msg))?;
+
+    // Repo dependencies are installed by pacman as dependencies, not as
+    // explicitly-requested packages. Some "repo" names are really AUR-provided
+    // virtuals pacman can't resolve, so a failure here is a warning, not a hard
+    // stop: the build proceeds and makepkg will surface anything truly missing.
+    if !repo_deps.is_empty() {
+        let status = std::process::Command::new("pacman")
+            .args(["-S", "--asdeps", "--noconfirm"])
+            .args(repo_deps.iter())
+            .status()?;
+        if !status.success() {
+            yellow!(fll, "A-install-repo-deps-fail");
+        }
+    }
+
     let clone_dir = crate::dirs::clones()?;
     let build_dir = crate::dirs::builds()?;
 
-    // TODO Sat Jan 15 18:50:43 2022
-    //
-    // Display cloning progress.
-    for p in to_clone {
-        clone_aur_repo(Some(&clone_dir), p)?;
+    // Packages the user asked for are installed explicitly; everything else in
+    // the resolved plan is a dependency and installed with `--asdeps`.
+    let explicit: HashSet<&str> = pkgs.iter().map(|p| p.as_str()).collect();
+
+    // Clone, build and install every AUR package in dependency order: leaves
+    // first, so that a package's dependencies are installed before it builds.
+    for p in &to_build {
+        let clone = clone_dir.join(p);
+        if !clone.is_dir() {
+            // TODO Sat Jan 15 18:50:43 2022
+            //
+            // Display cloning progress.
+            clone_aur_repo(Some(&clone_dir), p)?;
+        }
+        // Let the user audit the build scripts before anything is executed.
+        review_changes(fll, &clone)?;
+        let built = build_package(fll, containerize, image, &build_dir, &clone)?;
+        install_built(fll, &built, !explicit.contains(p.as_str()))?;
     }
 
     Ok(())
 }
 
+/// Install freshly built artifacts with `pacman -U`.
+///
+/// `as_dep` marks the packages as dependencies so they can be cleaned up by
+/// `pacman -Rs` once nothing needs them, matching how the repo dependencies
+/// were handed to `pacman -S --asdeps`.
+fn install_built(
+    fll: &FluentLanguageLoader,
+    artifacts: &[PathBuf],
+    as_dep: bool,
+) -> Result<(), Error> {
+    if artifacts.is_empty() {
+        return Ok(());
+    }
+
+    let mut cmd = std::process::Command::new("pacman");
+    cmd.args(["-U", "--noconfirm"]);
+    if as_dep {
+        cmd.arg("--asdeps");
+    }
+    let status = cmd.args(artifacts).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        red!(fll, "A-w-fail");
+        Err(Error::Silent)
+    }
+}
+
+/// Show the user the resolved install plan before any work begins.
+fn display_plan(fll: &FluentLanguageLoader, to_build: &[String], repo_deps: &HashSet<String>) {
+    if !repo_deps.is_empty() {
+        let mut repos: Vec<&String> = repo_deps.iter().collect();
+        repos.sort();
+        green!(fll, "A-install-repo-deps");
+        println!(
+            "  {}",
+            repos
+                .iter()
+                .map(|p| p.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+                .cyan()
+        );
+    }
+    green!(fll, "A-install-build-order");
+    for (i, p) in to_build.iter().enumerate() {
+        println!("  {}. {}", i + 1, p.bold());
+    }
+}
+
+/// Build a single cloned package, either inside a clean container or natively,
+/// returning the paths of the produced `*.pkg.tar.*` artifacts.
+fn build_package(
+    fll: &FluentLanguageLoader,
+    containerize: bool,
+    image: &str,
+    build_dir: &Path,
+    clone: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    if containerize {
+        let built = aura_core::container::build(image, &[], clone, build_dir)?;
+        for artifact in built.iter() {
+            debug!("Built artifact: {}", artifact.display());
+        }
+        Ok(built)
+    } else {
+        // Native fallback: run `makepkg` directly in the clone.
+        let status = std::process::Command::new("makepkg")
+            .args(["-s", "--noconfirm"])
+            .current_dir(clone)
+            .status()?;
+        if status.success() {
+            Ok(pkg_artifacts(clone))
+        } else {
+            red!(fll, "A-w-fail");
+            Err(Error::Silent)
+        }
+    }
+}
+
+/// Every `*.pkg.tar.*` artifact sitting in `dir`, excluding detached
+/// signatures.
+fn pkg_artifacts(dir: &Path) -> Vec<PathBuf> {
+    dir.read_dir()
+        .into_iter()
+        .flatten()
+        .filter_map(|rde| rde.ok())
+        .map(|de| de.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.contains(".pkg.tar.") && !n.ends_with(".sig"))
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
 fn real_packages<'a>(
     fll: &FluentLanguageLoader,
     pkgs: &'a [String],
@@ -335,6 +596,101 @@ fn real_packages<'a>(
     Ok((cloned, to_clone))
 }
 
+/// Files whose contents are security-relevant and must be audited before a
+/// build: the `PKGBUILD`, its generated `.SRCINFO`, and any install hooks.
+const REVIEW_PATHS: [&str; 3] = ["PKGBUILD", ".SRCINFO", "*.install"];
+
+/// The special git hash of the empty tree, used as the "from" side of the very
+/// first review so that the whole `PKGBUILD` shows up as an addition.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Audit a clone's build scripts before it is built.
+///
+/// The last commit the user approved is recorded in a small file inside the
+/// clone's own `.git` directory. When the freshly pulled HEAD differs, the diff
+/// of [`REVIEW_PATHS`] between the approved commit and HEAD is shown and the
+/// user is asked to accept or abort. Accepting advances the stored hash;
+/// aborting stops the operation. A clone that has never been reviewed is
+/// treated as a diff against the empty tree.
+fn review_changes(fll: &FluentLanguageLoader, clone: &Path) -> Result<(), Error> {
+    let pkg = clone
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    // Keep the approval record alongside the clone itself; it's ignored by the
+    // `REVIEW_PATHS` diff and never treated as a package directory by `refresh`.
+    let git_dir = clone.join(".git");
+    std::fs::create_dir_all(&git_dir)?;
+    let stored_path = git_dir.join("aura_reviewed");
+    let head = git_head(clone)?;
+    let stored = std::fs::read_to_string(&stored_path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    // Nothing new to review since the last approval.
+    if stored.as_deref() == Some(head.as_str()) {
+        return Ok(());
+    }
+
+    // Shallow clones/pulls can leave the previously-approved commit unreachable;
+    // in that case we can't trust a `git diff` against it, so fall back to
+    // reviewing the whole tree rather than silently approving nothing.
+    let from = match stored.as_deref() {
+        Some(h) if commit_exists(clone, h) => h,
+        _ => EMPTY_TREE,
+    };
+    aura!(fll, "A-install-review", package = pkg);
+    git_diff(clone, from, &head)?;
+
+    let msg = format!("{} {} ", fl!(fll, "proceed"), fl!(fll, "proceed-yes"));
+    crate::utils::prompt(&crate::a!(msg))?;
+
+    // Record the approved commit so it isn't re-reviewed next time.
+    std::fs::write(&stored_path, &head)?;
+    Ok(())
+}
+
+/// Whether `hash` names a commit that is reachable in the clone.
+fn commit_exists(clone: &Path, hash: &str) -> bool {
+    std::process::Command::new("git")
+        .arg("-C")
+        .arg(clone)
+        .args(["cat-file", "-e", &format!("{}^{{commit}}", hash)])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// The current HEAD commit hash of a clone.
+fn git_head(clone: &Path) -> Result<String, std::io::Error> {
+    let out = std::process::Command::new("git")
+        .arg("-C")
+        .arg(clone)
+        .args(["rev-parse", "HEAD"])
+        .output()?;
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// Print the diff of the review-relevant files between two commits.
+///
+/// Fails closed: if `git diff` itself errors (for example because `from` is no
+/// longer reachable), the caller must treat the review as unsatisfied rather
+/// than proceed on an empty diff.
+fn git_diff(clone: &Path, from: &str, to: &str) -> Result<(), Error> {
+    let status = std::process::Command::new("git")
+        .arg("-C")
+        .arg(clone)
+        .args(["diff", &format!("{}..{}", from, to), "--"])
+        .args(REVIEW_PATHS)
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Silent)
+    }
+}
+
 /// Clone a package's AUR repository and return the full path to the clone.
 fn clone_aur_repo(root: Option<&Path>, package: &str) -> Result<PathBuf, aura_core::git::Error> {
     let mut url: PathBuf = [AUR_BASE_URL, package].iter().collect();
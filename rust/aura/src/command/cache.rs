@@ -11,11 +11,16 @@ use i18n_embed_fl::fl;
 use log::debug;
 use pbr::ProgressBar;
 use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use ubyte::ToByteUnit;
 
+/// The number of versions to keep per package when cleaning the cache.
+pub const DEFAULT_KEEP: usize = 3;
+
 /// Print cache data for given packages.
 pub fn info(
     fll: FluentLanguageLoader,
@@ -75,10 +80,127 @@ pub fn info(
 }
 
 /// Print all package filepaths from the cache that match some search term.
-pub fn search(path: &Path, term: &str) -> Result<(), Error> {
+pub fn search(path: &Path, color: Color, term: &str) -> Result<(), Error> {
+    let terms = vec![term.to_lowercase()];
     let matches = core::cache::search(path, term)?;
     for file in matches {
-        println!("{}", file.path().display());
+        let shown = super::aur::highlight(color, &terms, &file.path().display().to_string());
+        println!("{}", shown);
+    }
+    Ok(())
+}
+
+/// Prune the package cache, keeping the newest `keep` versions per package.
+///
+/// Every file in the cache is grouped by package name and sorted newest-first
+/// using alpm's version comparison. The newest `keep` versions of each package
+/// are retained, as is whatever version is currently installed, and the rest
+/// are deleted. Deletion is gated behind the same confirmation prompt that
+/// [`backup`] uses, and the size to be reclaimed is reported before that
+/// prompt.
+pub fn clean(
+    fll: FluentLanguageLoader,
+    alpm: &Alpm,
+    path: &Path,
+    keep: usize,
+) -> Result<(), Error> {
+    let db = alpm.localdb();
+    let groups = grouped(path);
+
+    // Decide which files to remove: everything past the newest `keep` versions
+    // of each package, but never the currently-installed version.
+    let mut to_remove: Vec<PathBuf> = Vec::new();
+    for (name, mut versions) in groups {
+        versions.sort_by(|(a, _), (b, _)| vercmp(b, a));
+        let installed = db.pkg(name.as_str()).ok().map(|p| p.version().to_string());
+        for (i, (ver, file)) in versions.into_iter().enumerate() {
+            let keep_installed = installed.as_deref() == Some(ver.as_str());
+            if i >= keep && !keep_installed {
+                to_remove.push(file);
+            }
+        }
+    }
+
+    remove(fll, to_remove)
+}
+
+/// Remove every cached file whose package is no longer installed.
+pub fn clean_uninstalled(fll: FluentLanguageLoader, alpm: &Alpm, path: &Path) -> Result<(), Error> {
+    let db = alpm.localdb();
+    let to_remove: Vec<PathBuf> = grouped(path)
+        .into_iter()
+        .filter(|(name, _)| db.pkg(name.as_str()).is_err())
+        .flat_map(|(_, versions)| versions.into_iter().map(|(_, file)| file))
+        .collect();
+
+    remove(fll, to_remove)
+}
+
+/// Group every package file in the cache by package name.
+///
+/// Each group maps a package name to its `(version, path)` pairs.
+fn grouped(path: &Path) -> HashMap<String, Vec<(String, PathBuf)>> {
+    let mut groups: HashMap<String, Vec<(String, PathBuf)>> = HashMap::new();
+    let entries = match path.read_dir() {
+        Ok(rd) => rd,
+        Err(_) => return groups,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let file = entry.path();
+        if let Some((name, version)) = name_and_version(&file) {
+            groups.entry(name).or_default().push((version, file));
+        }
+    }
+    groups
+}
+
+/// Split an Arch package filename into its package name and version.
+///
+/// Package files are named `<name>-<version>-<rel>-<arch>.pkg.tar.<ext>`; the
+/// package name itself may contain dashes, so the trailing fields are split
+/// off from the right. Only actual package files are recognised: a name must
+/// carry the `.pkg.tar.` marker, and detached signatures (`*.sig`) are ignored
+/// so they don't masquerade as extra versions of their package.
+fn name_and_version(file: &Path) -> Option<(String, String)> {
+    let full = file.file_name()?.to_str()?;
+    if !full.contains(".pkg.tar.") || full.ends_with(".sig") {
+        return None;
+    }
+    let stem = full.split(".pkg.tar.").next()?;
+    let parts: Vec<&str> = stem.rsplitn(4, '-').collect();
+    match parts.as_slice() {
+        [_arch, rel, ver, name] => Some((name.to_string(), format!("{}-{}", ver, rel))),
+        _ => None,
+    }
+}
+
+/// Compare two version strings using alpm's package-version ordering.
+fn vercmp(a: &str, b: &str) -> Ordering {
+    alpm::vercmp(a, b)
+}
+
+/// Prompt for confirmation, then delete the given files and report the space
+/// reclaimed.
+fn remove(fll: FluentLanguageLoader, files: Vec<PathBuf>) -> Result<(), Error> {
+    if files.is_empty() {
+        aln!(fl!(fll, "cache-clean-none").green());
+        return Ok(());
+    }
+
+    let reclaimed: u64 = files
+        .iter()
+        .filter_map(|f| f.metadata().ok())
+        .map(|m| m.len())
+        .sum();
+    let size = format!("{}", reclaimed.bytes());
+    aln!(fl!(fll, "cache-clean-size", files = files.len(), size = size));
+
+    // Proceed if the user accepts.
+    let msg = format!("{} {} ", fl!(fll, "proceed"), fl!(fll, "proceed-yes"));
+    crate::utils::prompt(&a!(msg))?;
+
+    for file in files {
+        std::fs::remove_file(file)?;
     }
     Ok(())
 }